@@ -1,145 +1,765 @@
+use std::thread;
+use std::time::Duration;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
 use model::*;
 use errors::*;
 use url::{Url};
-use serde_json::{from_str};
+use serde_json::{from_str, json, Value};
 
 use tungstenite::{connect};
-use tungstenite::protocol::WebSocket;
+use tungstenite::protocol::{Message, WebSocket};
 use tungstenite::client::AutoStream;
+use tungstenite::stream::StreamSwitcher;
 use tungstenite::handshake::client::{Response};
 
+use futures::{SinkExt, Stream, StreamExt};
+use std::pin::Pin;
+use std::task::{Context, Poll};
+
 // https://github.com/binance-exchange/binance-official-api-docs/blob/master/web-socket-streams.md
 
-static WEBSOCKET_URL : &'static str = "wss://stream.binance.com:9443/ws/";
+static WEBSOCKET_URL_SPOT : &'static str = "wss://stream.binance.com:9443";
+static WEBSOCKET_URL_USDM : &'static str = "wss://fstream.binance.com";
+static WEBSOCKET_URL_COINM : &'static str = "wss://dstream.binance.com";
+static WEBSOCKET_URL_VANILLA : &'static str = "wss://vstream.binance.com";
+
+/// Which Binance product the socket talks to — each has its own base host, but the same
+/// `/ws/{endpoint}` and `/stream?streams=...` path shapes.
+#[derive(Clone)]
+pub enum WebsocketMarket {
+    Spot,
+    USDM,
+    COINM,
+    Vanilla,
+}
+
+impl WebsocketMarket {
+    fn base_url(&self) -> &'static str {
+        match *self {
+            WebsocketMarket::Spot => WEBSOCKET_URL_SPOT,
+            WebsocketMarket::USDM => WEBSOCKET_URL_USDM,
+            WebsocketMarket::COINM => WEBSOCKET_URL_COINM,
+            WebsocketMarket::Vanilla => WEBSOCKET_URL_VANILLA,
+        }
+    }
+}
+
+/// Selects which base endpoint `WebSockets::connect` talks to.
+///
+/// `Default` keeps the historical single-stream behaviour
+/// (`wss://stream.binance.com:9443/ws/{endpoint}`), `MultiStream` combines several
+/// stream names behind one socket (`wss://stream.binance.com:9443/stream?streams=a/b/c`),
+/// and `Custom` lets callers point at a testnet or proxy URL of their own.
+#[derive(Clone)]
+pub enum WebsocketAPI {
+    Default,
+    MultiStream,
+    Custom(String),
+}
+
+static RECONNECT_INITIAL_BACKOFF : u64 = 1;
+static RECONNECT_MAX_BACKOFF : u64 = 60;
+static RECONNECT_MAX_ATTEMPTS : u32 = 10;
+
+/// How long a blocking `read_message()` call waits for a frame before giving `event_loop` a
+/// chance to re-check `keep_running`. Keeps a stuck-open socket from hiding a shutdown request.
+static READ_TIMEOUT_SECS : u64 = 1;
+
+impl WebsocketAPI {
+    fn params(self, market: &WebsocketMarket, subscription: &str) -> String {
+        match self {
+            WebsocketAPI::Default => format!("{}/ws/{}", market.base_url(), subscription),
+            WebsocketAPI::MultiStream => format!("{}/stream?streams={}", market.base_url(), subscription),
+            WebsocketAPI::Custom(url) => format!("{}{}", url, subscription),
+        }
+    }
+}
 
-static OUTBOUND_ACCOUNT_INFO : &'static str = "outboundAccountInfo";
-static EXECUTION_REPORT : &'static str = "executionReport";
+/// Best bid/ask for a symbol, pushed on every order book top-of-book change. Unlike the other
+/// streams the spot payload carries no `"e"` event-type field, so `parse` recognises it by its
+/// `u`/`b` shape; the futures payload is tagged `bookTicker` and goes through
+/// `TaggedWebsocketEvent`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BookTickerEvent {
+    #[serde(rename = "u")]
+    pub update_id: u64,
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "b")]
+    pub best_bid: String,
+    #[serde(rename = "B")]
+    pub best_bid_qty: String,
+    #[serde(rename = "a")]
+    pub best_ask: String,
+    #[serde(rename = "A")]
+    pub best_ask_qty: String,
+}
 
-static KLINE : &'static str = "kline";
-static AGGREGATED_TRADE : &'static str = "aggTrade";
-static TRADE : &'static str = "trade";
-static DEPTH_DIFF : &'static str = "depthUpdate";
-static ORDERBOOK : &'static str = "lastUpdateId";
+#[derive(Debug, Clone, Deserialize)]
+pub struct DayTickerEvent {
+    #[serde(rename = "E")]
+    pub event_time: u64,
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "p")]
+    pub price_change: String,
+    #[serde(rename = "P")]
+    pub price_change_percent: String,
+    #[serde(rename = "w")]
+    pub weighted_avg_price: String,
+    #[serde(rename = "x")]
+    pub prev_close_price: String,
+    #[serde(rename = "c")]
+    pub current_close: String,
+    #[serde(rename = "Q")]
+    pub current_close_qty: String,
+    #[serde(rename = "b")]
+    pub best_bid: String,
+    #[serde(rename = "B")]
+    pub best_bid_qty: String,
+    #[serde(rename = "a")]
+    pub best_ask: String,
+    #[serde(rename = "A")]
+    pub best_ask_qty: String,
+    #[serde(rename = "o")]
+    pub open: String,
+    #[serde(rename = "h")]
+    pub high: String,
+    #[serde(rename = "l")]
+    pub low: String,
+    #[serde(rename = "v")]
+    pub volume: String,
+    #[serde(rename = "q")]
+    pub quote_volume: String,
+    #[serde(rename = "O")]
+    pub open_time: u64,
+    #[serde(rename = "C")]
+    pub close_time: u64,
+    #[serde(rename = "F")]
+    pub first_trade_id: i64,
+    #[serde(rename = "L")]
+    pub last_trade_id: i64,
+    #[serde(rename = "n")]
+    pub num_trades: u64,
+}
 
-pub trait UserStreamEventHandler {
-    fn account_update_handler(&self, event: &AccountUpdateEvent);
-    fn order_trade_handler(&self, event: &OrderTradeEvent);
+#[derive(Debug, Clone, Deserialize)]
+pub struct MiniTickerEvent {
+    #[serde(rename = "E")]
+    pub event_time: u64,
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "c")]
+    pub close: String,
+    #[serde(rename = "o")]
+    pub open: String,
+    #[serde(rename = "h")]
+    pub high: String,
+    #[serde(rename = "l")]
+    pub low: String,
+    #[serde(rename = "v")]
+    pub volume: String,
+    #[serde(rename = "q")]
+    pub quote_volume: String,
 }
 
-pub trait MarketEventHandler {
-    fn aggregated_trades_handler(&self, event: &AggTradeEvent);
-    fn trade_handler(&self, event: &TradeEvent);
-    fn partial_orderbook_handler(&self, orderbook: &OrderBook);
-    fn depth_diff_handler(&self, event: &DepthDiffEvent);
+#[derive(Debug, Clone, Deserialize)]
+pub struct BalanceUpdateEvent {
+    #[serde(rename = "E")]
+    pub event_time: u64,
+    #[serde(rename = "a")]
+    pub asset: String,
+    #[serde(rename = "d")]
+    pub delta: String,
+    #[serde(rename = "T")]
+    pub clear_time: u64,
 }
 
-pub trait KlineEventHandler {
-    fn kline_handler(&self, event: &KlineEvent);
+#[derive(Debug, Clone, Deserialize)]
+pub struct LiquidationOrder {
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "S")]
+    pub side: String,
+    #[serde(rename = "o")]
+    pub order_type: String,
+    #[serde(rename = "f")]
+    pub time_in_force: String,
+    #[serde(rename = "q")]
+    pub original_quantity: String,
+    #[serde(rename = "p")]
+    pub price: String,
+    #[serde(rename = "ap")]
+    pub average_price: String,
+    #[serde(rename = "X")]
+    pub order_status: String,
+    #[serde(rename = "l")]
+    pub last_filled_quantity: String,
+    #[serde(rename = "z")]
+    pub filled_accumulated_quantity: String,
+    #[serde(rename = "T")]
+    pub trade_time: u64,
 }
 
-pub struct WebSockets {
-    socket: Option<(WebSocket<AutoStream>, Response)>, 
-    user_stream_handler: Option<Box<UserStreamEventHandler>>,
-    market_handler: Option<Box<MarketEventHandler>>,
-    kline_handler: Option<Box<KlineEventHandler>>,
+/// `forceOrder` — a liquidation fill on USD-M/COIN-M futures.
+#[derive(Debug, Clone, Deserialize)]
+pub struct LiquidationEvent {
+    #[serde(rename = "E")]
+    pub event_time: u64,
+    #[serde(rename = "o")]
+    pub liquidation_order: LiquidationOrder,
 }
 
-impl WebSockets {
+/// `markPriceUpdate` — mark price and funding rate, pushed every 3s on futures markets.
+#[derive(Debug, Clone, Deserialize)]
+pub struct MarkPriceEvent {
+    #[serde(rename = "E")]
+    pub event_time: u64,
+    #[serde(rename = "s")]
+    pub symbol: String,
+    #[serde(rename = "p")]
+    pub mark_price: String,
+    #[serde(rename = "i")]
+    pub index_price: String,
+    #[serde(rename = "r")]
+    pub funding_rate: String,
+    #[serde(rename = "T")]
+    pub next_funding_time: u64,
+}
 
-    pub fn new() -> WebSockets {
+/// `continuous_kline` — klines keyed off a contract pair rather than a traded symbol.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ContinuousKlineEvent {
+    #[serde(rename = "E")]
+    pub event_time: u64,
+    #[serde(rename = "ps")]
+    pub pair: String,
+    #[serde(rename = "ct")]
+    pub contract_type: String,
+    #[serde(rename = "k")]
+    pub kline: Kline,
+}
+
+/// `indexPriceKline` — klines of the futures index price.
+#[derive(Debug, Clone, Deserialize)]
+pub struct IndexKlineEvent {
+    #[serde(rename = "E")]
+    pub event_time: u64,
+    #[serde(rename = "ps")]
+    pub pair: String,
+    #[serde(rename = "k")]
+    pub kline: Kline,
+}
+
+/// `listenKeyExpired` — the futures user-data stream's listen key needs renewing.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ListenKeyExpiredEvent {
+    #[serde(rename = "E")]
+    pub event_time: u64,
+}
+
+/// Every payload a Binance websocket stream can emit, tagged by Binance's own `"e"` event-type
+/// field. Three exceptions are recognised by shape instead: the partial orderbook stream (no
+/// `"e"`, has `"lastUpdateId"`), the book ticker stream (no `"e"`, has `"u"`/`"b"`/`"a"`), and
+/// the all-market ticker array streams (a bare JSON array of ticker objects).
+#[derive(Debug)]
+pub enum WebsocketEvent {
+    AccountUpdate(AccountUpdateEvent),
+    BalanceUpdate(BalanceUpdateEvent),
+    OrderTrade(OrderTradeEvent),
+    AggrTrades(AggTradeEvent),
+    Trade(TradeEvent),
+    OrderBook(OrderBook),
+    DepthDiff(DepthDiffEvent),
+    Kline(KlineEvent),
+    DayTicker(DayTickerEvent),
+    DayTickerAll(Vec<DayTickerEvent>),
+    MiniTicker(MiniTickerEvent),
+    MiniTickerAll(Vec<MiniTickerEvent>),
+    BookTicker(BookTickerEvent),
+    Liquidation(LiquidationEvent),
+    MarkPrice(MarkPriceEvent),
+    ContinuousKline(ContinuousKlineEvent),
+    IndexKline(IndexKlineEvent),
+    ListenKeyExpired(ListenKeyExpiredEvent),
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "e")]
+enum TaggedWebsocketEvent {
+    #[serde(rename = "outboundAccountInfo")]
+    AccountUpdate(AccountUpdateEvent),
+    #[serde(rename = "balanceUpdate")]
+    BalanceUpdate(BalanceUpdateEvent),
+    #[serde(rename = "executionReport")]
+    OrderTrade(OrderTradeEvent),
+    #[serde(rename = "aggTrade")]
+    AggrTrades(AggTradeEvent),
+    #[serde(rename = "trade")]
+    Trade(TradeEvent),
+    #[serde(rename = "depthUpdate")]
+    DepthDiff(DepthDiffEvent),
+    #[serde(rename = "kline")]
+    Kline(KlineEvent),
+    #[serde(rename = "24hrTicker")]
+    DayTicker(DayTickerEvent),
+    #[serde(rename = "24hrMiniTicker")]
+    MiniTicker(MiniTickerEvent),
+    #[serde(rename = "forceOrder")]
+    Liquidation(LiquidationEvent),
+    #[serde(rename = "markPriceUpdate")]
+    MarkPrice(MarkPriceEvent),
+    #[serde(rename = "continuous_kline")]
+    ContinuousKline(ContinuousKlineEvent),
+    #[serde(rename = "indexPriceKline")]
+    IndexKline(IndexKlineEvent),
+    #[serde(rename = "listenKeyExpired")]
+    ListenKeyExpired(ListenKeyExpiredEvent),
+    // Futures book ticker carries an "e" tag, unlike the spot stream (see the untagged fallback
+    // in `WebsocketEvent::parse`), so it needs its own arm here too.
+    #[serde(rename = "bookTicker")]
+    BookTicker(BookTickerEvent),
+}
+
+impl From<TaggedWebsocketEvent> for WebsocketEvent {
+    fn from(tagged: TaggedWebsocketEvent) -> Self {
+        match tagged {
+            TaggedWebsocketEvent::AccountUpdate(e) => WebsocketEvent::AccountUpdate(e),
+            TaggedWebsocketEvent::BalanceUpdate(e) => WebsocketEvent::BalanceUpdate(e),
+            TaggedWebsocketEvent::OrderTrade(e) => WebsocketEvent::OrderTrade(e),
+            TaggedWebsocketEvent::AggrTrades(e) => WebsocketEvent::AggrTrades(e),
+            TaggedWebsocketEvent::Trade(e) => WebsocketEvent::Trade(e),
+            TaggedWebsocketEvent::DepthDiff(e) => WebsocketEvent::DepthDiff(e),
+            TaggedWebsocketEvent::Kline(e) => WebsocketEvent::Kline(e),
+            TaggedWebsocketEvent::DayTicker(e) => WebsocketEvent::DayTicker(e),
+            TaggedWebsocketEvent::MiniTicker(e) => WebsocketEvent::MiniTicker(e),
+            TaggedWebsocketEvent::Liquidation(e) => WebsocketEvent::Liquidation(e),
+            TaggedWebsocketEvent::MarkPrice(e) => WebsocketEvent::MarkPrice(e),
+            TaggedWebsocketEvent::ContinuousKline(e) => WebsocketEvent::ContinuousKline(e),
+            TaggedWebsocketEvent::IndexKline(e) => WebsocketEvent::IndexKline(e),
+            TaggedWebsocketEvent::ListenKeyExpired(e) => WebsocketEvent::ListenKeyExpired(e),
+            TaggedWebsocketEvent::BookTicker(e) => WebsocketEvent::BookTicker(e),
+        }
+    }
+}
+
+impl WebsocketEvent {
+    fn parse(msg: &str) -> Result<WebsocketEvent> {
+        let value: Value = from_str(msg)?;
+
+        if let Value::Array(items) = value {
+            return Self::parse_array(items);
+        }
+
+        if value.get("lastUpdateId").is_some() {
+            return Ok(WebsocketEvent::OrderBook(serde_json::from_value(value)?));
+        }
+
+        // Spot's book ticker stream carries no "e" tag at all; futures' does and is matched by
+        // `TaggedWebsocketEvent`'s own `bookTicker` arm below.
+        if value.get("e").is_none() && value.get("u").is_some() && value.get("b").is_some() {
+            return Ok(WebsocketEvent::BookTicker(serde_json::from_value(value)?));
+        }
+
+        let tagged: TaggedWebsocketEvent = serde_json::from_value(value)?;
+        Ok(tagged.into())
+    }
+
+    /// The all-market `!ticker@arr` / `!miniTicker@arr` streams push a bare JSON array rather
+    /// than a tagged object, so the element type is inferred from the first entry's `"e"` field.
+    fn parse_array(items: Vec<Value>) -> Result<WebsocketEvent> {
+        match items.get(0).and_then(|item| item.get("e")).and_then(Value::as_str) {
+            Some("24hrTicker") => Ok(WebsocketEvent::DayTickerAll(serde_json::from_value(Value::Array(items))?)),
+            Some("24hrMiniTicker") => Ok(WebsocketEvent::MiniTickerAll(serde_json::from_value(Value::Array(items))?)),
+            _ => bail!("Unrecognized array-based websocket event"),
+        }
+    }
+}
+
+pub struct WebSockets<'a> {
+    socket: Option<(WebSocket<AutoStream>, Response)>,
+    endpoint: Option<(WebsocketMarket, WebsocketAPI, String)>,
+    keep_running: Arc<AtomicBool>,
+    handler: Box<FnMut(WebsocketEvent) -> Result<()> + 'a>,
+}
+
+impl<'a> WebSockets<'a> {
+
+    pub fn new<Callback>(handler: Callback) -> WebSockets<'a>
+    where
+        Callback: FnMut(WebsocketEvent) -> Result<()> + 'a,
+    {
         WebSockets {
             socket: None,
-            user_stream_handler: None, 
-            market_handler: None,     
-            kline_handler: None, 
+            endpoint: None,
+            keep_running: Arc::new(AtomicBool::new(true)),
+            handler: Box::new(handler),
         }
     }
 
-    pub fn connect(&mut self, endpoint: String) -> Result<()> {        
-        let wss: String = format!("{}{}", WEBSOCKET_URL, endpoint);
+    /// Returns a cloneable handle that another thread can use to stop `event_loop`. `event_loop`
+    /// only observes the flag between reads, so the socket is given a `READ_TIMEOUT_SECS` read
+    /// timeout at connect time — that bounds how long a call to this handle can take to be
+    /// noticed, and lets `read_message()` return periodically instead of blocking forever.
+    /// The handle cannot close the handshake itself (the socket is owned by whichever thread
+    /// is running `event_loop`); `event_loop` performs that teardown itself once it observes
+    /// the flag and breaks out.
+    pub fn keep_running_handle(&self) -> Arc<AtomicBool> {
+        self.keep_running.clone()
+    }
+
+    /// Stops `event_loop` and closes the websocket handshake cleanly. Must be called from the
+    /// thread that owns this `WebSockets`, and not while `event_loop` is running, since it
+    /// takes `&mut self`; a caller on another thread should use `keep_running_handle` instead.
+    pub fn disconnect(&mut self) -> Result<()> {
+        self.keep_running.store(false, Ordering::Relaxed);
+        if let Some((ref mut socket, _)) = self.socket {
+            socket.close(None)?;
+        }
+        Ok(())
+    }
+
+    pub fn connect(&mut self, endpoint: String) -> Result<()> {
+        self.connect_with_api(WebsocketMarket::Spot, WebsocketAPI::Default, endpoint)
+    }
+
+    pub fn connect_with_api(&mut self, market: WebsocketMarket, api: WebsocketAPI, subscription: String) -> Result<()> {
+        self.endpoint = Some((market.clone(), api.clone(), subscription.clone()));
+        self.open_socket(market, api, subscription)
+    }
+
+    fn open_socket(&mut self, market: WebsocketMarket, api: WebsocketAPI, subscription: String) -> Result<()> {
+        let wss: String = api.params(&market, &subscription);
         let url = Url::parse(&wss)?;
 
         match connect(url) {
             Ok(answer) => {
+                Self::set_read_timeout(answer.0.get_ref(), Some(Duration::from_secs(READ_TIMEOUT_SECS)))?;
                 self.socket = Some(answer);
                 return Ok(());
             },
             Err(e) => {
                 bail!(format!("Error during handshake {}", e));
             },
-        } 
+        }
     }
 
-    pub fn add_user_stream_handler<H>(&mut self, handler: H)
-    where
-        H: UserStreamEventHandler + 'static,
-    {
-        self.user_stream_handler = Some(Box::new(handler));
+    /// `AutoStream` is `Plain(TcpStream)` or `Tls(TlsStream<TcpStream>)` — a `wss://` connection
+    /// (the only kind Binance offers) is always the latter, whose `TcpStream` is one level
+    /// deeper via `TlsStream::get_ref()`, so `set_read_timeout` has to be applied per-variant.
+    fn set_read_timeout(stream: &AutoStream, timeout: Option<Duration>) -> Result<()> {
+        match *stream {
+            StreamSwitcher::Plain(ref s) => s.set_read_timeout(timeout)?,
+            StreamSwitcher::Tls(ref s) => s.get_ref().set_read_timeout(timeout)?,
+        }
+        Ok(())
     }
 
-    pub fn add_market_handler<H>(&mut self, handler: H)
-    where
-        H: MarketEventHandler + 'static,
-    {
-        self.market_handler = Some(Box::new(handler));
-    }    
+    /// Reconnects using the endpoint remembered from the last `connect`/`connect_with_api` call,
+    /// retrying with capped exponential backoff until a handshake succeeds. Used by `event_loop`
+    /// to ride out transient connection drops instead of panicking the whole process. Gives up
+    /// and bubbles up the last handshake error after `RECONNECT_MAX_ATTEMPTS` failed attempts,
+    /// so a genuinely permanent failure (bad URL, rejected auth) doesn't spin forever.
+    fn reconnect(&mut self) -> Result<()> {
+        let (market, api, subscription) = match self.endpoint {
+            Some((ref market, ref api, ref subscription)) => (market.clone(), api.clone(), subscription.clone()),
+            None => bail!("Cannot reconnect: no endpoint has been connected yet"),
+        };
 
-    pub fn add_kline_handler<H>(&mut self, handler: H)
-    where
-        H: KlineEventHandler + 'static,
-    {
-        self.kline_handler = Some(Box::new(handler));
-    }  
+        let mut backoff = RECONNECT_INITIAL_BACKOFF;
+        let mut last_err = None;
+        for _ in 0..RECONNECT_MAX_ATTEMPTS {
+            if !self.keep_running.load(Ordering::Relaxed) {
+                return Ok(());
+            }
 
-    pub fn event_loop(&mut self) {
-        loop {
-            if let Some(ref mut socket) = self.socket {
-                let msg: String = socket.0.read_message().unwrap().into_text().unwrap();
+            match self.open_socket(market.clone(), api.clone(), subscription.clone()) {
+                Ok(()) => return Ok(()),
+                Err(e) => {
+                    last_err = Some(e);
+                    thread::sleep(Duration::from_secs(backoff));
+                    backoff = (backoff * 2).min(RECONNECT_MAX_BACKOFF);
+                },
+            }
+        }
 
-                if msg.find(OUTBOUND_ACCOUNT_INFO) != None {
-                    let account_update: AccountUpdateEvent = from_str(msg.as_str()).unwrap();
+        Err(last_err.unwrap_or_else(|| "Exhausted reconnect attempts".into()))
+    }
 
-                    if let Some(ref h) = self.user_stream_handler {
-                        h.account_update_handler(&account_update);
-                    }
-                } else if msg.find(EXECUTION_REPORT) != None {
-                    let order_trade: OrderTradeEvent = from_str(msg.as_str()).unwrap();
+    /// A connection error is recoverable if it looks like a transient drop (the peer closing
+    /// the socket, or the underlying TCP connection resetting); anything else — bad handshake
+    /// data, protocol violations, DNS failures, connection-refused — is treated as permanent
+    /// since retrying won't fix it and should bubble up instead of burning the reconnect budget.
+    fn is_recoverable(err: &tungstenite::Error) -> bool {
+        match *err {
+            tungstenite::Error::ConnectionClosed | tungstenite::Error::AlreadyClosed => true,
+            tungstenite::Error::Io(ref io_err) => Self::is_recoverable_io(io_err),
+            _ => false,
+        }
+    }
 
-                    if let Some(ref h) = self.user_stream_handler {
-                        h.order_trade_handler(&order_trade);
-                    }
-                } else if msg.find(AGGREGATED_TRADE) != None {
-                    let trades: AggTradeEvent = from_str(msg.as_str()).unwrap();
+    /// Only the I/O errors that typically mean "the peer dropped an otherwise-healthy
+    /// connection" are worth a reconnect; everything else (connection refused, DNS
+    /// resolution failure, permission errors, ...) is permanent.
+    fn is_recoverable_io(io_err: &::std::io::Error) -> bool {
+        match io_err.kind() {
+            ::std::io::ErrorKind::ConnectionReset
+            | ::std::io::ErrorKind::BrokenPipe
+            | ::std::io::ErrorKind::UnexpectedEof => true,
+            _ => false,
+        }
+    }
 
-                    if let Some(ref h) = self.market_handler {
-                        h.aggregated_trades_handler(&trades);
-                    }
-                } else if msg.find(TRADE) != None {
-                    let trade: TradeEvent = from_str(msg.as_str()).unwrap();
+    /// `READ_TIMEOUT_SECS` elapsing with no frame surfaces as a `WouldBlock`/`TimedOut` I/O
+    /// error, not a dropped connection — `event_loop` should just loop around and re-check
+    /// `keep_running` rather than treating it as recoverable-via-reconnect or fatal.
+    fn is_timeout(io_err: &::std::io::Error) -> bool {
+        match io_err.kind() {
+            ::std::io::ErrorKind::WouldBlock | ::std::io::ErrorKind::TimedOut => true,
+            _ => false,
+        }
+    }
 
-                    if let Some(ref h) = self.market_handler {
-                        h.trade_handler(&trade);
-                    }
-                } else if msg.find(KLINE) != None {
-                    let kline: KlineEvent = from_str(msg.as_str()).unwrap();
+    pub fn event_loop(&mut self) -> Result<()> {
+        while self.keep_running.load(Ordering::Relaxed) {
+            let read = match self.socket {
+                Some(ref mut socket) => socket.0.read_message(),
+                None => break,
+            };
 
-                    if let Some(ref h) = self.kline_handler {
-                        h.kline_handler(&kline);
+            // Binance pings the socket roughly every 3 minutes and expects the pong tungstenite
+            // already answers automatically; only `Text` frames carry an event payload to parse.
+            let raw: String = match read {
+                Ok(Message::Text(text)) => text,
+                Ok(Message::Ping(_)) | Ok(Message::Pong(_)) | Ok(Message::Close(_)) | Ok(Message::Binary(_)) => continue,
+                Err(tungstenite::Error::Io(ref io_err)) if Self::is_timeout(io_err) => continue,
+                Err(e) => {
+                    if Self::is_recoverable(&e) {
+                        self.reconnect()?;
+                        continue;
+                    } else {
+                        return Err(e.into());
                     }
-                } else if msg.find(ORDERBOOK) != None {
-                    let partial_orderbook: OrderBook = from_str(msg.as_str()).unwrap();
+                },
+            };
+            let msg: String = Self::unwrap_combined_stream(&raw);
+            let event = WebsocketEvent::parse(&msg)?;
+            (self.handler)(event)?;
+        }
 
-                    if let Some(ref h) = self.market_handler {
-                        h.partial_orderbook_handler(&partial_orderbook);
-                    }
-                } else if msg.find(DEPTH_DIFF) != None {
-                    let depth_diff: DepthDiffEvent = from_str(msg.as_str()).unwrap();
+        // `keep_running` was flipped from another thread (or we broke out above); tear down the
+        // handshake from here since the socket can only be touched by the thread that owns it.
+        if let Some((ref mut socket, _)) = self.socket {
+            socket.close(None)?;
+        }
 
-                    if let Some(ref h) = self.market_handler {
-                        h.depth_diff_handler(&depth_diff);
-                    }
-                }
+        Ok(())
+    }
+
+    /// Multi-stream sockets wrap every frame as `{"stream":"...","data":{...}}`; unwrap the
+    /// `data` field so the rest of `event_loop` can keep dispatching on the raw event payload
+    /// regardless of which connection mode produced it.
+    fn unwrap_combined_stream(raw: &str) -> String {
+        match from_str::<Value>(raw) {
+            Ok(Value::Object(ref map)) if map.contains_key("stream") && map.contains_key("data") => {
+                map["data"].to_string()
+            },
+            _ => raw.to_string(),
+        }
+    }
+}
+
+/// Individual stream types that can be subscribed to over the async websocket, formatted as
+/// `{symbol}@{type}` in the control message `WebSocketsAsync::subscribe` sends after connecting.
+pub enum AsyncStreamType {
+    Trade,
+    AggTrade,
+    BookTicker,
+    PartialDepth(u8),
+    DayTicker,
+}
+
+impl AsyncStreamType {
+    fn suffix(&self) -> String {
+        match *self {
+            AsyncStreamType::Trade => "trade".to_owned(),
+            AsyncStreamType::AggTrade => "aggTrade".to_owned(),
+            AsyncStreamType::BookTicker => "bookTicker".to_owned(),
+            AsyncStreamType::PartialDepth(levels) => format!("depth{}", levels),
+            AsyncStreamType::DayTicker => "ticker".to_owned(),
+        }
+    }
+}
+
+fn stream_param(symbol: &str, stream_type: &AsyncStreamType) -> String {
+    format!("{}@{}", symbol.to_lowercase(), stream_type.suffix())
+}
+
+/// Async counterpart to `WebSockets`, built on `tokio-tungstenite` and exposing incoming events
+/// as a `futures::Stream` instead of a blocking `event_loop`/handler-closure pair. Subscriptions
+/// are sent as `{"method":"SUBSCRIBE","params":[...],"id":N}` control frames right after the
+/// handshake, with `id` taken from a per-socket counter that increments on every call so
+/// repeated `subscribe` calls can be correlated to their ack frames.
+pub struct WebSocketsAsync {
+    socket: tokio_tungstenite::WebSocketStream<tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>>,
+    next_id: u64,
+}
+
+impl WebSocketsAsync {
+    /// Connects to `market`'s raw websocket endpoint without subscribing to anything yet — use
+    /// this when the caller wants to drive subscriptions itself via `subscribe`.
+    pub async fn connect(market: WebsocketMarket) -> Result<WebSocketsAsync> {
+        let url = Url::parse(&format!("{}/ws", market.base_url()))?;
+        let (socket, _) = tokio_tungstenite::connect_async(url).await?;
+        Ok(WebSocketsAsync { socket, next_id: 1 })
+    }
+
+    /// Connects to `market` and immediately subscribes to `(symbol, stream_type)` pairs by
+    /// sending the `SUBSCRIBE` control message Binance expects on a freshly opened socket.
+    pub async fn connect_with_subscriptions(
+        market: WebsocketMarket,
+        streams: &[(&str, AsyncStreamType)],
+    ) -> Result<WebSocketsAsync> {
+        let mut socket = WebSocketsAsync::connect(market).await?;
+        socket.subscribe(streams).await?;
+        Ok(socket)
+    }
+
+    /// Sends a `SUBSCRIBE` control message for the given `(symbol, stream_type)` pairs, tagging
+    /// it with the next id from this socket's counter.
+    pub async fn subscribe(&mut self, streams: &[(&str, AsyncStreamType)]) -> Result<()> {
+        let params: Vec<String> = streams.iter().map(|(symbol, stream_type)| stream_param(symbol, stream_type)).collect();
+        let id = self.next_id;
+        self.next_id += 1;
+        let request = json!({
+            "method": "SUBSCRIBE",
+            "params": params,
+            "id": id,
+        });
+
+        self.socket.send(tokio_tungstenite::tungstenite::Message::Text(request.to_string())).await?;
+        Ok(())
+    }
+}
+
+impl Stream for WebSocketsAsync {
+    type Item = Result<WebsocketEvent>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<Self::Item>> {
+        // Binance pings the socket roughly every 3 minutes; tokio-tungstenite answers the pong
+        // itself, so only `Text` frames carry an event payload worth parsing. Control frames are
+        // skipped by re-polling rather than surfacing them as `Err` items.
+        loop {
+            match Pin::new(&mut self.socket).poll_next(cx) {
+                Poll::Ready(Some(Ok(tokio_tungstenite::tungstenite::Message::Text(text)))) => {
+                    let msg = WebSockets::unwrap_combined_stream(&text);
+                    return Poll::Ready(Some(WebsocketEvent::parse(&msg)));
+                },
+                Poll::Ready(Some(Ok(_))) => continue,
+                Poll::Ready(Some(Err(e))) => return Poll::Ready(Some(Err(e.into()))),
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Pending => return Poll::Pending,
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unwrap_combined_stream_extracts_data_field() {
+        let raw = r#"{"stream":"btcusdt@bookTicker","data":{"u":1,"s":"BTCUSDT","b":"1.0","B":"2.0","a":"3.0","A":"4.0"}}"#;
+
+        let unwrapped = WebSockets::unwrap_combined_stream(raw);
+        let value: Value = from_str(&unwrapped).unwrap();
+
+        assert_eq!(value["s"], "BTCUSDT");
+        assert!(value.get("stream").is_none());
+    }
+
+    #[test]
+    fn unwrap_combined_stream_passes_through_plain_frames() {
+        let raw = r#"{"u":1,"s":"BTCUSDT","b":"1.0","B":"2.0","a":"3.0","A":"4.0"}"#;
+
+        assert_eq!(WebSockets::unwrap_combined_stream(raw), raw);
+    }
+
+    #[test]
+    fn parse_recognizes_partial_orderbook_by_last_update_id() {
+        let raw = r#"{"lastUpdateId":160,"bids":[["0.0024","10","[]"]],"asks":[["0.0026","100","[]"]]}"#;
+
+        match WebsocketEvent::parse(raw).unwrap() {
+            WebsocketEvent::OrderBook(_) => (),
+            other => panic!("expected OrderBook, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_recognizes_spot_book_ticker_with_no_event_tag() {
+        let raw = r#"{"u":400900217,"s":"BNBUSDT","b":"25.35","B":"31.21","a":"25.36","A":"40.66"}"#;
+
+        match WebsocketEvent::parse(raw).unwrap() {
+            WebsocketEvent::BookTicker(event) => assert_eq!(event.symbol, "BNBUSDT"),
+            other => panic!("expected BookTicker, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_recognizes_futures_book_ticker_with_event_tag() {
+        let raw = r#"{"e":"bookTicker","u":400900217,"s":"BNBUSDT","b":"25.35","B":"31.21","a":"25.36","A":"40.66"}"#;
+
+        match WebsocketEvent::parse(raw).unwrap() {
+            WebsocketEvent::BookTicker(event) => assert_eq!(event.symbol, "BNBUSDT"),
+            other => panic!("expected BookTicker, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_dispatches_tagged_events_by_e_field() {
+        let raw = r#"{"e":"listenKeyExpired","E":1499405658658}"#;
+
+        match WebsocketEvent::parse(raw).unwrap() {
+            WebsocketEvent::ListenKeyExpired(event) => assert_eq!(event.event_time, 1499405658658),
+            other => panic!("expected ListenKeyExpired, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_rejects_unrecognized_tagged_event() {
+        let raw = r#"{"e":"somethingUnheardOf","E":1}"#;
+
+        assert!(WebsocketEvent::parse(raw).is_err());
+    }
+
+    fn mini_ticker_json(symbol: &str) -> String {
+        format!(
+            r#"{{"e":"24hrMiniTicker","E":1499405658658,"s":"{}","c":"1","o":"1","h":"1","l":"1","v":"1","q":"1"}}"#,
+            symbol
+        )
+    }
+
+    #[test]
+    fn parse_array_dispatches_all_market_mini_ticker_stream() {
+        let raw = format!("[{},{}]", mini_ticker_json("BNBBTC"), mini_ticker_json("ETHBTC"));
+
+        match WebsocketEvent::parse(&raw).unwrap() {
+            WebsocketEvent::MiniTickerAll(events) => {
+                assert_eq!(events.len(), 2);
+                assert_eq!(events[0].symbol, "BNBBTC");
+                assert_eq!(events[1].symbol, "ETHBTC");
+            },
+            other => panic!("expected MiniTickerAll, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn parse_array_rejects_unknown_element_shape() {
+        let raw = r#"[{"foo":"bar"}]"#;
+
+        assert!(WebsocketEvent::parse(raw).is_err());
+    }
+}